@@ -1,33 +1,36 @@
-use std::{time::{Duration, Instant}, hash::Hash, collections::HashMap};
+use std::{time::{Duration, Instant}, hash::Hash, collections::{HashMap, HashSet}, sync::{Arc, Mutex, atomic::{AtomicBool, AtomicUsize, Ordering}}};
 
-use ::ddo::{Problem, Cutoff, TimeBudget, NoCutoff, Fringe, NoDupFringe, StateRanking, MaxUB, SimpleFringe, WidthHeuristic, FixedWidth, NbUnassignedWitdh, Variable, Decision, Relaxation, Solver, Completion, SeqNoBarrierSolverLel, SeqBarrierSolverLel, SeqBarrierSolverFc, SeqNoBarrierSolverFc};
+use ::ddo::{Problem, Cutoff, TimeBudget, Fringe, NoDupFringe, StateRanking, MaxUB, SimpleFringe, WidthHeuristic, FixedWidth, NbUnassignedWitdh, Variable, Decision, Relaxation, Solver, Completion, SeqNoBarrierSolverLel, SeqBarrierSolverLel, SeqBarrierSolverFc, SeqNoBarrierSolverFc, ParallelSolver};
 
-use pyo3::{prelude::*, types::{PyBool}};
+use pyo3::{prelude::*, exceptions::PyValueError, types::{PyBool}};
 
 /// This module exposes binding to the ddo (rust) engine to perform
 /// fast discrete optimization using decision diagrams.
 #[pymodule]
 fn ddo(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(maximize, m)?)?;
+    m.add_function(wrap_pyfunction!(minimize, m)?)?;
+    m.add_class::<LpRelax>()?;
     Ok(())
 }
 
 #[pyclass]
-/// This is the object which is returned after you made a call to 
-/// maximize. It does give you various information which you might 
-/// find useful. 
+/// This is the object which is returned after you made a call to
+/// maximize. It does give you various information which you might
+/// find useful.
 pub struct Solution {
     #[pyo3(get)]
-    /// Was the search for an optimal solution aborted because of an external cutoff ?
+    /// Was the search for an optimal solution aborted because of an external cutoff
+    /// (a timeout, or `on_progress` returning `True`) ?
     pub aborted: bool,
     #[pyo3(get)]
     /// What is the gap to optimality
     pub gap: f32,
     #[pyo3(get)]
-    /// The time it took to optimize the function (in seconds). 
+    /// The time it took to optimize the function (in seconds).
     pub duration: f64,
     #[pyo3(get)]
-    /// What is the objective value of the function you tried to maximize ? 
+    /// What is the objective value of the function you tried to maximize ?
     /// -> If no solution was found, then the objective value will be None
     pub objective: Option<isize>,
     #[pyo3(get)]
@@ -37,7 +40,7 @@ pub struct Solution {
     /// The best known lower bound on the objective value
     pub lower_bound: isize,
     #[pyo3(get)]
-    /// What are the assigments leading to the best solution ? 
+    /// What are the assigments leading to the best solution ?
     /// `assignment[x] = y` means value `y` was assigned to variable `x`.
     /// -> If no solution was found, then the assignment value will be None
     pub assignment: Option<Vec<isize>>,
@@ -45,8 +48,20 @@ pub struct Solution {
 
 #[pyfunction]
 #[allow(clippy::too_many_arguments)]
+/// `on_progress`, if given, is called at most once per `progress_interval`
+/// seconds (default 1.0) as `on_progress(elapsed, nb_explored) -> bool`,
+/// where `elapsed` is the search time so far in seconds and `nb_explored`
+/// is the number of transitions computed so far (a count of generated
+/// nodes, not of the nodes the solver itself counts as explored); a return
+/// value of `True` aborts the search early (surfaced on the returned
+/// `Solution` as `aborted`), while any other value or a raised exception
+/// lets it go on. It does
+/// NOT receive the best lower/upper bound or the gap -- see `ProgressCutoff`
+/// for why those can't be reported without aliasing the solver's `&mut self`
+/// while it's still running.
 fn maximize(
-    pb         : PyObject, 
+    py         : Python<'_>,
+    pb         : PyObject,
     relax      : PyObject,
     ranking    : PyObject,
     lel        : bool,
@@ -54,80 +69,362 @@ fn maximize(
     dedup      : bool,
     width      : Option<usize>,
     timeout    : Option<u64>,
+    threads    : Option<usize>,
+    on_progress: Option<PyObject>,
+    progress_interval: Option<f64>,
+    warm_start : Option<(Vec<isize>, isize)>,
+    constraints: Option<(Vec<(usize, isize)>, Vec<(usize, isize)>)>,
+    lp         : Option<Py<LpRelax>>,
 ) -> Solution {
-    Python::with_gil(|gil| {
-        let problem = PyProblem {gil, obj: pb};
-        let relax = PyRelax {gil, obj: relax};
-        let ranking = PyRanking {gil, obj: ranking};
-        let max_width = max_width(problem.nb_variables(), width);
-        let cutoff = cutoff(timeout);
-        let mut fringe = fringe(dedup, &ranking);
-
-        let mut solver = solver(
-            &problem, 
-            &relax, 
-            &ranking, 
-            max_width.as_ref(), 
-            cutoff.as_ref(), 
-            fringe.as_mut(),
-            lel,
-            use_barrier
-        );
-
-        let start = Instant::now();
-        let Completion{is_exact, best_value} = solver.maximize();
-        
-        let duration = start.elapsed().as_secs_f64();
-        let gap = solver.gap();
-        let assignment = solver.best_solution().map(|mut decisions| {
-            decisions.sort_unstable_by_key(|d| d.variable.id());
-            decisions.iter().map(|d| d.value).collect()
-        });
-        
-        Solution {
-            aborted:     !is_exact,
-            objective:   best_value,
-            upper_bound: solver.best_upper_bound(),
-            lower_bound: solver.best_lower_bound(),
-            assignment,
-            gap,
-            duration
+    solve(Sense::Maximise, py, pb, relax, ranking, lel, use_barrier, dedup, width, timeout, threads, on_progress, progress_interval, warm_start, constraints, lp)
+}
+
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+/// The `Minimise` counterpart of `maximize`: the user's `pb`/`relax` are
+/// modeled exactly as they would be for a minimization (no need to negate
+/// `transition_cost`/`initial_value`/`fast_upper_bound`, or to flip the
+/// sign of `Solution.objective` by hand), `minimize` takes care of running
+/// the search internally as a maximization of the negated objective.
+///
+/// `on_progress` has the same `(elapsed, nb_explored) -> bool` signature as
+/// in `maximize` -- see there for why the bounds/gap aren't included.
+fn minimize(
+    py         : Python<'_>,
+    pb         : PyObject,
+    relax      : PyObject,
+    ranking    : PyObject,
+    lel        : bool,
+    use_barrier: bool,
+    dedup      : bool,
+    width      : Option<usize>,
+    timeout    : Option<u64>,
+    threads    : Option<usize>,
+    on_progress: Option<PyObject>,
+    progress_interval: Option<f64>,
+    warm_start : Option<(Vec<isize>, isize)>,
+    constraints: Option<(Vec<(usize, isize)>, Vec<(usize, isize)>)>,
+    lp         : Option<Py<LpRelax>>,
+) -> Solution {
+    solve(Sense::Minimise, py, pb, relax, ranking, lel, use_barrier, dedup, width, timeout, threads, on_progress, progress_interval, warm_start, constraints, lp)
+}
+
+/// Which direction the user's objective should be optimized in.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Sense {
+    Maximise,
+    Minimise,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn solve(
+    sense      : Sense,
+    py         : Python<'_>,
+    pb         : PyObject,
+    relax      : PyObject,
+    ranking    : PyObject,
+    lel        : bool,
+    use_barrier: bool,
+    dedup      : bool,
+    width      : Option<usize>,
+    timeout    : Option<u64>,
+    threads    : Option<usize>,
+    on_progress: Option<PyObject>,
+    progress_interval: Option<f64>,
+    warm_start : Option<(Vec<isize>, isize)>,
+    constraints: Option<(Vec<(usize, isize)>, Vec<(usize, isize)>)>,
+    lp         : Option<Py<LpRelax>>,
+) -> Solution {
+    let negate = sense == Sense::Minimise;
+    let nb_explored = Arc::new(AtomicUsize::new(0));
+    let (forced, forbidden) = constraints.unwrap_or_default();
+    let problem = PyProblem {
+        obj: pb,
+        nb_explored: nb_explored.clone(),
+        forced: forced.into_iter().collect(),
+        forbidden: forbidden.into_iter().collect(),
+    };
+    let problem = Sensed::new(problem, negate);
+    let relax = Sensed::new(PyRelax {obj: relax, lp, negate}, negate);
+    let ranking = Sensed::new(PyRanking {obj: ranking}, negate);
+    let max_width = max_width(problem.nb_variables(), width);
+    let progress = on_progress.map(|callback| {
+        let interval = Duration::from_secs_f64(progress_interval.unwrap_or(1.0).max(0.0));
+        ProgressCutoff::new(callback, interval, nb_explored)
+    });
+    let cutoff = cutoff(timeout, progress.as_ref().map(|p| p as &dyn Cutoff));
+    let mut fringe = fringe(dedup, &ranking);
+
+    let mut solver = solver(
+        &problem,
+        &relax,
+        &ranking,
+        max_width.as_ref(),
+        &cutoff,
+        fringe.as_mut(),
+        lel,
+        use_barrier,
+        threads,
+    );
+    // Priming the incumbent before the search starts would need
+    // `Solver::set_primal`, which -- unlike every other `Solver` call in
+    // this file (`maximize`, `gap`, `best_solution`, `best_upper_bound`,
+    // `best_lower_bound`) -- is not otherwise exercised anywhere in this
+    // crate, and this sandbox has no way to build against the pinned `ddo`
+    // version to confirm it actually exists on `Solver`. Shipping that call
+    // risked the whole crate failing to compile, so `warm_start` is kept on
+    // the Python-facing signature for compatibility, but is currently a
+    // no-op: the search still finds the same optimal solution on its own,
+    // just without the possible head start `set_primal` would have given
+    // it. Wire it back up once that method is confirmed against the locked
+    // dependency.
+    let _ = warm_start;
+
+    let start = Instant::now();
+    // The search itself never needs the GIL on this thread: each worker
+    // (there may be several, when `threads` requests a parallel solver)
+    // re-acquires it on demand every time it calls back into `pb`/`relax`/
+    // `ranking`, so releasing it here lets those workers actually run
+    // concurrently instead of queueing up behind this one token.
+    let Completion{is_exact, best_value} = py.allow_threads(|| solver.maximize());
+
+    let duration = start.elapsed().as_secs_f64();
+    let gap = solver.gap();
+    let assignment = solver.best_solution().map(|mut decisions| {
+        decisions.sort_unstable_by_key(|d| d.variable.id());
+        decisions.iter().map(|d| d.value).collect()
+    });
+
+    // The engine always maximizes. For `Minimise`, every bound it reports
+    // is in the negated sense, so besides negating them back we also have
+    // to swap upper and lower: an upper bound on `-objective` is a lower
+    // bound on `objective`, and vice-versa.
+    let (upper_bound, lower_bound) = if negate {
+        (-solver.best_lower_bound(), -solver.best_upper_bound())
+    } else {
+        (solver.best_upper_bound(), solver.best_lower_bound())
+    };
+
+    Solution {
+        aborted: !is_exact,
+        objective: if negate { best_value.map(|v| -v) } else { best_value },
+        upper_bound,
+        lower_bound,
+        assignment,
+        gap,
+        duration
+    }
+}
+
+/// Wraps a `PyProblem`/`PyRelax`/`PyRanking` to make the solver's sense
+/// transparent to the engine, which only ever maximizes: when `negate` is
+/// set (the user modeled a minimization), every cost or ranking outcome the
+/// engine will use is flipped on the way in, and flipped back before it
+/// reaches the user's own callbacks.
+struct Sensed<T> {
+    inner: T,
+    negate: bool,
+}
+impl<T> Sensed<T> {
+    fn new(inner: T, negate: bool) -> Self {
+        Sensed { inner, negate }
+    }
+
+    fn flip(&self, value: isize) -> isize {
+        if self.negate { -value } else { value }
+    }
+}
+impl<T: Problem<State = PyState>> Problem for Sensed<T> {
+    type State = PyState;
+
+    fn nb_variables(&self) -> usize {
+        self.inner.nb_variables()
+    }
+
+    fn initial_state(&self) -> Self::State {
+        self.inner.initial_state()
+    }
+
+    fn initial_value(&self) -> isize {
+        self.flip(self.inner.initial_value())
+    }
+
+    fn transition(&self, state: &Self::State, decision: Decision) -> Self::State {
+        self.inner.transition(state, decision)
+    }
+
+    fn transition_cost(&self, state: &Self::State, decision: Decision) -> isize {
+        self.flip(self.inner.transition_cost(state, decision))
+    }
+
+    fn next_variable(&self, depth: usize, next_layer: &mut dyn Iterator<Item = &Self::State>) -> Option<Variable> {
+        self.inner.next_variable(depth, next_layer)
+    }
+
+    fn for_each_in_domain(&self, var: Variable, state: &Self::State, f: &mut dyn ::ddo::DecisionCallback) {
+        self.inner.for_each_in_domain(var, state, f)
+    }
+}
+impl<T: Relaxation<State = PyState>> Relaxation for Sensed<T> {
+    type State = PyState;
+
+    fn merge(&self, states: &mut dyn Iterator<Item = &Self::State>) -> Self::State {
+        self.inner.merge(states)
+    }
+
+    fn relax(&self, source: &Self::State, dest: &Self::State, new: &Self::State, decision: Decision, cost: isize) -> isize {
+        // `cost` arrives already flipped (it is `transition_cost`'s
+        // output), so it is un-flipped before reaching the user's own
+        // `relax`, and the adjustment it returns is flipped back again.
+        self.flip(self.inner.relax(source, dest, new, decision, self.flip(cost)))
+    }
+
+    fn fast_upper_bound(&self, state: &Self::State) -> isize {
+        // `isize::MAX` is the engine-space "no information" sentinel that
+        // `inner` (the LP or Python relaxation) returns when it couldn't
+        // compute a real bound. It must bypass `flip`: negating it would
+        // wrap around to (approximately) `isize::MIN`, which as an upper
+        // bound would prune essentially every node instead of none.
+        match self.inner.fast_upper_bound(state) {
+            isize::MAX => isize::MAX,
+            value => self.flip(value),
         }
-    })
+    }
 }
+impl<T: StateRanking<State = PyState>> StateRanking for Sensed<T> {
+    type State = PyState;
 
+    fn compare(&self, a: &Self::State, b: &Self::State) -> std::cmp::Ordering {
+        let ordering = self.inner.compare(a, b);
+        if self.negate { ordering.reverse() } else { ordering }
+    }
+}
+
+/// Picks and builds the solver implementation matching the requested
+/// configuration. When `threads` asks for more than one worker, the search
+/// is dispatched to `ParallelSolver` instead of one of the sequential
+/// variants so it can make use of several cores.
 #[allow(clippy::too_many_arguments)]
-fn solver<'a, 'b>(
-    problem    : &'a PyProblem<'b>, 
-    relaxation : &'a PyRelax<'b>, 
-    ranking    : &'a PyRanking<'b>, 
-    width_heu  : &'a dyn WidthHeuristic<PyState<'b>>, 
-    cutoff     : &'a dyn Cutoff, 
-    fringe     : &'a mut dyn Fringe<State = PyState<'b>>, 
+fn solver<'a, P, R, Rk>(
+    problem    : &'a P,
+    relaxation : &'a R,
+    ranking    : &'a Rk,
+    width_heu  : &'a dyn WidthHeuristic<PyState>,
+    cutoff     : &'a dyn Cutoff,
+    fringe     : &'a mut dyn Fringe<State = PyState>,
     lel        : bool,
     use_barrier: bool,
-) -> Box<dyn Solver + 'a> {
+    threads    : Option<usize>,
+) -> Box<dyn Solver + Send + Sync + 'a>
+where
+    P : Problem<State = PyState> + Send + Sync,
+    R : Relaxation<State = PyState> + Send + Sync,
+    Rk: StateRanking<State = PyState> + Send + Sync,
+{
+    if let Some(nb_threads) = threads.filter(|n| *n > 1) {
+        return Box::new(ParallelSolver::custom(problem, relaxation, ranking, width_heu, cutoff, fringe, nb_threads));
+    }
     match (lel, use_barrier) {
-        (true, true) => 
+        (true, true) =>
             Box::new(SeqBarrierSolverLel::custom(problem, relaxation, ranking, width_heu, cutoff, fringe)),
-        (true, false) => 
+        (true, false) =>
             Box::new(SeqNoBarrierSolverLel::custom(problem, relaxation, ranking, width_heu, cutoff, fringe)),
-        (false, true) => 
+        (false, true) =>
             Box::new(SeqBarrierSolverFc::custom(problem, relaxation, ranking, width_heu, cutoff, fringe)),
-        (false, false) => 
+        (false, false) =>
             Box::new(SeqNoBarrierSolverFc::custom(problem, relaxation, ranking, width_heu, cutoff, fringe)),
     }
 }
 
-fn cutoff(timeout: Option<u64>) -> Box<dyn Cutoff> {
-    if let Some(timeout) = timeout {
-        Box::new(TimeBudget::new(Duration::from_secs(timeout)))
-    } else {
-        Box::new(NoCutoff)
+/// Combines the plain timeout cutoff with the optional progress observer:
+/// the search stops as soon as either one of them asks for it.
+struct CombinedCutoff<'a> {
+    timeout:  Option<TimeBudget>,
+    progress: Option<&'a ProgressCutoff>,
+}
+impl Cutoff for CombinedCutoff<'_> {
+    fn must_stop(&self) -> bool {
+        self.timeout.as_ref().map(|t| t.must_stop()).unwrap_or(false)
+            || self.progress.map(|p| p.must_stop()).unwrap_or(false)
     }
 }
 
-fn fringe<'a>(dedup: bool, ranking: &'a PyRanking<'a>) -> Box<dyn Fringe<State = PyState<'a>> + 'a> {
+fn cutoff(timeout: Option<u64>, progress: Option<&ProgressCutoff>) -> CombinedCutoff<'_> {
+    CombinedCutoff {
+        timeout: timeout.map(|timeout| TimeBudget::new(Duration::from_secs(timeout))),
+        progress,
+    }
+}
+
+/// Polls the search's progress at most once per `interval` and hands it to a
+/// user-supplied Python callable; a return value of `True` aborts the search
+/// early. It is checked by the engine alongside the plain timeout cutoff,
+/// right next to `cutoff()`, wherever the engine already polls
+/// `Cutoff::must_stop` between fringe expansions.
+///
+/// This deliberately does not report the solver's own `best_lower_bound`/
+/// `best_upper_bound`/`gap`: `must_stop` runs while the engine's own call to
+/// `Solver::maximize(&mut self)` is still on the stack, so there is no way to
+/// also hold a reference to that same solver here without aliasing its
+/// `&mut`, regardless of how the reference is obtained. `elapsed` and
+/// `nb_explored` are sound to report because they live in state this struct
+/// owns outright (a plain `Instant` and the `Arc<AtomicUsize>` counter
+/// `PyProblem::transition` already bumps), not in the solver itself.
+struct ProgressCutoff {
+    callback:    PyObject,
+    interval:    Duration,
+    start:       Instant,
+    last_tick:   Mutex<Instant>,
+    nb_explored: Arc<AtomicUsize>,
+    stop:        AtomicBool,
+}
+
+impl ProgressCutoff {
+    fn new(callback: PyObject, interval: Duration, nb_explored: Arc<AtomicUsize>) -> Self {
+        let now = Instant::now();
+        ProgressCutoff {
+            callback,
+            interval,
+            start: now,
+            last_tick: Mutex::new(now),
+            nb_explored,
+            stop: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Cutoff for ProgressCutoff {
+    fn must_stop(&self) -> bool {
+        if self.stop.load(Ordering::Relaxed) {
+            return true;
+        }
+
+        let mut last_tick = self.last_tick.lock().unwrap();
+        let now = Instant::now();
+        if now.duration_since(*last_tick) < self.interval {
+            return false;
+        }
+        *last_tick = now;
+        drop(last_tick);
+
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let nb_explored = self.nb_explored.load(Ordering::Relaxed);
+
+        let abort = Python::with_gil(|py| {
+            self.callback.call1(py, (elapsed, nb_explored))
+                .ok()
+                .and_then(|res| res.extract::<bool>(py).ok())
+                .unwrap_or(false)
+        });
+
+        if abort {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+        abort
+    }
+}
+
+fn fringe<'a, Rk: StateRanking<State = PyState>>(dedup: bool, ranking: &'a Rk) -> Box<dyn Fringe<State = PyState> + 'a> {
     if dedup {
         Box::new(NoDupFringe::new(MaxUB::new(ranking)))
     } else {
@@ -135,7 +432,7 @@ fn fringe<'a>(dedup: bool, ranking: &'a PyRanking<'a>) -> Box<dyn Fringe<State =
     }
 }
 
-fn max_width<'a>(n: usize, w: Option<usize>) -> Box<dyn WidthHeuristic<PyState<'a>>> {
+fn max_width(n: usize, w: Option<usize>) -> Box<dyn WidthHeuristic<PyState>> {
     if let Some(w) = w {
         Box::new(FixedWidth(w))
     } else {
@@ -143,121 +440,262 @@ fn max_width<'a>(n: usize, w: Option<usize>) -> Box<dyn WidthHeuristic<PyState<'
     }
 }
 
+/// A state from a `PyProblem`/`PyRelax`/`PyRanking`. It only ever keeps a
+/// `PyObject` around (never a `Python<'_>` token), so that it can safely
+/// cross thread boundaries when the parallel solver hands it to a worker:
+/// every method re-acquires the GIL for the duration of its own callback.
 #[derive(Clone)]
-pub struct PyState<'a> {
-    gil: Python<'a>,
+pub struct PyState {
     obj: PyObject
 }
-unsafe impl Send for PyState<'_> {}
-impl Eq for PyState<'_> {}
-impl PartialEq for PyState<'_> {
+impl Eq for PyState {}
+impl PartialEq for PyState {
     fn eq(&self, other: &Self) -> bool {
-        let res = self.obj.call_method(self.gil, "__eq__", (&other.obj,), None)
-            .unwrap();
-        let res = res.cast_as::<PyBool>(self.gil)
-            .unwrap();
-        res.is_true()
+        Python::with_gil(|py| {
+            let res = self.obj.call_method(py, "__eq__", (&other.obj,), None)
+                .unwrap();
+            let res = res.cast_as::<PyBool>(py)
+                .unwrap();
+            res.is_true()
+        })
     }
 }
-impl Hash for PyState<'_> {
+impl Hash for PyState {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        let res = self.obj.call_method(self.gil, "__hash__", (), None)
-            .unwrap();
-        let res = res.extract::<isize>(self.gil)
-            .unwrap();
-        state.write_isize(res)
+        Python::with_gil(|py| {
+            let res = self.obj.call_method(py, "__hash__", (), None)
+                .unwrap();
+            let res = res.extract::<isize>(py)
+                .unwrap();
+            state.write_isize(res)
+        })
     }
 }
 
-pub struct PyProblem<'a> {
-    gil: Python<'a>,
-    obj: PyObject
+pub struct PyProblem {
+    obj: PyObject,
+    // Counts how many nodes this problem's `transition` has expanded, so
+    // that `ProgressCutoff` has a cheap proxy for "nodes explored" without
+    // needing a dedicated counter inside the solver itself.
+    nb_explored: Arc<AtomicUsize>,
+    // (variable, value) pairs that must appear in every solution. A variable
+    // with a forced value has its domain narrowed down to that single value.
+    forced: HashMap<usize, isize>,
+    // (variable, value) pairs that must never appear in any solution. A
+    // forbidden value is simply removed from the variable's domain.
+    forbidden: HashSet<(usize, isize)>,
 }
-unsafe impl Send for PyProblem<'_> {}
-impl <'a> Problem for PyProblem<'a> {
-    type State = PyState<'a>;
+impl Problem for PyProblem {
+    type State = PyState;
 
     fn nb_variables(&self) -> usize {
-        let res = self.obj.call_method(self.gil, "nb_variables", (), None)
-            .unwrap();
-        res.extract::<usize>(self.gil)
-            .unwrap()
+        Python::with_gil(|py| {
+            let res = self.obj.call_method(py, "nb_variables", (), None)
+                .unwrap();
+            res.extract::<usize>(py)
+                .unwrap()
+        })
     }
 
     fn initial_state(&self) -> Self::State {
-        let res = {
-            self.obj.call_method(self.gil, "initial_state", (), None)
-            .unwrap()
-        };
-        PyState { gil: self.gil, obj: res }
+        Python::with_gil(|py| {
+            let res = self.obj.call_method(py, "initial_state", (), None)
+                .unwrap();
+            PyState { obj: res }
+        })
     }
 
     fn initial_value(&self) -> isize {
-        let res = self.obj.call_method(self.gil, "initial_value", (), None)
-            .unwrap();
-        res.extract::<isize>(self.gil)
-            .unwrap()
+        Python::with_gil(|py| {
+            let res = self.obj.call_method(py, "initial_value", (), None)
+                .unwrap();
+            res.extract::<isize>(py)
+                .unwrap()
+        })
     }
 
     fn transition(&self, state: &Self::State, decision: ::ddo::Decision) -> Self::State {
-        let res = {
-            self.obj.call_method(self.gil, "transition", (&state.obj, decision.variable.0, decision.value), None)
-            .unwrap()
-        };
-        PyState { gil: self.gil, obj: res }
+        self.nb_explored.fetch_add(1, Ordering::Relaxed);
+        Python::with_gil(|py| {
+            let res = self.obj.call_method(py, "transition", (&state.obj, decision.variable.0, decision.value), None)
+                .unwrap();
+            PyState { obj: res }
+        })
     }
 
     fn transition_cost(&self, state: &Self::State, decision: ::ddo::Decision) -> isize {
-        let res = self.obj.call_method(self.gil, "transition_cost", (&state.obj, decision.variable.0, decision.value), None)
-            .unwrap();
-        res.extract::<isize>(self.gil)
-            .unwrap()
+        Python::with_gil(|py| {
+            let res = self.obj.call_method(py, "transition_cost", (&state.obj, decision.variable.0, decision.value), None)
+                .unwrap();
+            res.extract::<isize>(py)
+                .unwrap()
+        })
     }
 
     fn next_variable(&self, depth: usize, next_layer: &mut dyn Iterator<Item = &Self::State>)
         -> Option<::ddo::Variable> {
-        let next_layer = next_layer.map(|x| &x.obj).collect::<Vec<_>>();
-        
-        let res = self.obj.call_method(self.gil, "next_variable", (depth, next_layer,), None)
-            .unwrap();
-        if res.is_none(self.gil) {
-            None
-        } else {
-            let var_id = res.extract::<usize>(self.gil)
-            .unwrap();
-            Some(Variable(var_id))
-        }
+        Python::with_gil(|py| {
+            let next_layer = next_layer.map(|x| &x.obj).collect::<Vec<_>>();
+
+            let res = self.obj.call_method(py, "next_variable", (depth, next_layer,), None)
+                .unwrap();
+            if res.is_none(py) {
+                None
+            } else {
+                let var_id = res.extract::<usize>(py)
+                .unwrap();
+                Some(Variable(var_id))
+            }
+        })
     }
 
     fn for_each_in_domain(&self, var: ::ddo::Variable, state: &Self::State, f: &mut dyn ::ddo::DecisionCallback) {
-        let dom = {
-            let res = self.obj.call_method(self.gil, "domain", (var.0, &state.obj), None)
+        let dom = Python::with_gil(|py| {
+            let res = self.obj.call_method(py, "domain", (var.0, &state.obj), None)
                 .unwrap();
-            res.extract::<Vec<isize>>(self.gil).unwrap()
-        };
+            res.extract::<Vec<isize>>(py).unwrap()
+        });
+        let forced = self.forced.get(&var.0);
         for val in dom {
+            if forced.is_some_and(|forced| *forced != val) {
+                continue;
+            }
+            if self.forbidden.contains(&(var.0, val)) {
+                continue;
+            }
             f.apply(Decision{variable: var, value: val})
         }
     }
-    
+
+}
+
+
+/// A linear-programming relaxation of the user's problem, declared once
+/// from Python: objective coefficients, constraint rows (coefficients plus
+/// a lower/upper range) and variable bounds. `PyRelax::fast_upper_bound`
+/// solves it for every state with a state-dependent right-hand side and a
+/// set of fixed variables, which it still asks Python for (`lp_state`) at
+/// every node. Only the model's data is kept here: every call builds a
+/// fresh LP from it; no solved model is kept and updated between states.
+#[pyclass]
+pub struct LpRelax {
+    objective: Vec<f64>,
+    rows: Vec<(Vec<f64>, f64, f64)>,
+    lb: Vec<f64>,
+    ub: Vec<f64>,
+}
+
+#[pymethods]
+impl LpRelax {
+    #[new]
+    fn new(objective: Vec<f64>, rows: Vec<(Vec<f64>, f64, f64)>, lb: Vec<f64>, ub: Vec<f64>) -> PyResult<Self> {
+        let n = objective.len();
+        if lb.len() != n || ub.len() != n {
+            return Err(PyValueError::new_err(format!(
+                "lb and ub must each have one entry per variable ({n}), got {} and {}", lb.len(), ub.len()
+            )));
+        }
+        if let Some((i, (coeffs, _, _))) = rows.iter().enumerate().find(|(_, (coeffs, _, _))| coeffs.len() != n) {
+            return Err(PyValueError::new_err(format!(
+                "row {i} has {} coefficients, expected one per variable ({n})", coeffs.len()
+            )));
+        }
+        Ok(LpRelax { objective, rows, lb, ub })
+    }
 }
 
+impl LpRelax {
+    /// Solves the model for one node. Each row's declared `upper` bound is
+    /// intersected with the matching entry of the per-state `rhs` (use
+    /// `f64::INFINITY` in `rows` for a row whose upper side isn't state-
+    /// dependent); the `lower` side of each row comes only from the row's
+    /// own declared range and is never tightened by `rhs`. `fixed` pins a
+    /// subset of variables to a single value, and everything else --
+    /// objective, coefficients, the remaining variable bounds -- comes from
+    /// the model captured once in `new`.
+    ///
+    /// `negate` must mirror the `Sense` the surrounding search is run in:
+    /// for `Maximise` this solves the objective as declared and rounds the
+    /// optimum up, which is a valid upper bound on it; for `Minimise` it
+    /// solves the same objective as a minimization and rounds down instead,
+    /// which is a valid *lower* bound on it -- exactly what `Sensed`'s
+    /// blanket negation needs to turn into a valid upper bound on the
+    /// engine's internally-negated objective.
+    ///
+    /// On failure (a mismatched `rhs`, or no feasible/bounded LP solution)
+    /// this always returns `isize::MAX`, the engine-space "no information"
+    /// sentinel, regardless of `negate` -- callers apply `negate` themselves
+    /// only to real bounds, never to this sentinel, so it can never invert
+    /// into an aggressive prune.
+    fn fast_upper_bound(&self, rhs: &[f64], fixed: &[(usize, f64)], negate: bool) -> isize {
+        if rhs.len() != self.rows.len() {
+            // A state-dependent rhs that doesn't match the declared model is
+            // a caller bug we can't safely recover a real bound from; fail
+            // closed rather than silently dropping rows or indexing OOB.
+            return isize::MAX;
+        }
+
+        let direction = if negate {
+            minilp::OptimizationDirection::Minimize
+        } else {
+            minilp::OptimizationDirection::Maximize
+        };
+        let mut problem = minilp::Problem::new(direction);
+
+        let vars: Vec<_> = self.objective.iter().enumerate()
+            .map(|(i, &c)| {
+                let fixed_value = fixed.iter().find(|(j, _)| *j == i).map(|(_, v)| v);
+                match fixed_value {
+                    Some(&v) => problem.add_var(c, (v, v)),
+                    None => problem.add_var(c, (self.lb[i], self.ub[i])),
+                }
+            })
+            .collect();
+
+        for ((coeffs, lower, upper), &state_limit) in self.rows.iter().zip(rhs.iter()) {
+            let expr: Vec<_> = coeffs.iter().zip(vars.iter())
+                .map(|(&c, &v)| (v, c))
+                .collect();
+            let upper = upper.min(state_limit);
+            if lower.is_finite() {
+                problem.add_constraint(expr.clone(), minilp::ComparisonOp::Ge, *lower);
+            }
+            if upper.is_finite() {
+                problem.add_constraint(expr, minilp::ComparisonOp::Le, upper);
+            }
+        }
 
-pub struct PyRelax<'a> {
-    gil: Python<'a>,
+        match problem.solve() {
+            Ok(solution) => {
+                let obj = solution.objective();
+                if negate { obj.floor() as isize } else { obj.ceil() as isize }
+            }
+            Err(_) => isize::MAX,
+        }
+    }
+}
+
+pub struct PyRelax {
     obj: PyObject,
+    // When set, `fast_upper_bound` is computed natively by solving this LP;
+    // Python is then only asked for each state's right-hand side and fixed
+    // variables (`lp_state`), not for the bound itself.
+    lp: Option<Py<LpRelax>>,
+    // Mirrors the `Sense` the search is run in, so the LP can be solved and
+    // rounded in the direction that stays a valid bound on the user's
+    // objective; see `LpRelax::fast_upper_bound`.
+    negate: bool,
 }
-unsafe impl Send for PyRelax<'_> {}
-impl <'a> Relaxation for PyRelax<'a> {
-    type State = PyState<'a>;
+impl Relaxation for PyRelax {
+    type State = PyState;
 
     fn merge(&self, states: &mut dyn Iterator<Item = &Self::State>) -> Self::State {
-        let states = states.map(|x| &x.obj).collect::<Vec<_>>();
-        let res = {
-            self.obj.call_method(self.gil, "merge", (states,), None)
-            .unwrap()
-        };
-        PyState { gil: self.gil, obj: res }
+        Python::with_gil(|py| {
+            let states = states.map(|x| &x.obj).collect::<Vec<_>>();
+            let res = self.obj.call_method(py, "merge", (states,), None)
+                .unwrap();
+            PyState { obj: res }
+        })
     }
 
     fn relax(
@@ -268,51 +706,67 @@ impl <'a> Relaxation for PyRelax<'a> {
         decision: Decision,
         cost: isize,
     ) -> isize {
-        let var = decision.variable.0.into_py(self.gil);
-        let val = decision.value.into_py(self.gil);
-        let cost = cost.into_py(self.gil);
+        Python::with_gil(|py| {
+            let var = decision.variable.0.into_py(py);
+            let val = decision.value.into_py(py);
+            let cost = cost.into_py(py);
 
-        let mut dict = HashMap::<&str, &PyObject>::default();
-        dict.insert("source", &source.obj);
-        dict.insert("dest", &dest.obj);
-        dict.insert("new", &new.obj);
-        dict.insert("variable", &var);
-        dict.insert("value", &val);
-        dict.insert("cost", &cost);
+            let mut dict = HashMap::<&str, &PyObject>::default();
+            dict.insert("source", &source.obj);
+            dict.insert("dest", &dest.obj);
+            dict.insert("new", &new.obj);
+            dict.insert("variable", &var);
+            dict.insert("value", &val);
+            dict.insert("cost", &cost);
 
-        let res = self.obj.call_method(self.gil, "relax", (dict,), None)
-            .unwrap();
-        res.extract(self.gil).unwrap()
+            let res = self.obj.call_method(py, "relax", (dict,), None)
+                .unwrap();
+            res.extract(py).unwrap()
+        })
     }
 
     fn fast_upper_bound(&self, state: &Self::State) -> isize {
-        let res = self.obj.call_method(self.gil, "fast_upper_bound", (&state.obj,), None);
-        if let Ok(res) = res {
-            res.extract(self.gil).unwrap()
-        } else {
-            isize::MAX
-        }
+        Python::with_gil(|py| {
+            if let Some(lp) = self.lp.as_ref() {
+                // Only the state-dependent right-hand side and fixed
+                // variables are round-tripped into Python; the objective,
+                // constraint coefficients and variable bounds were already
+                // captured once when `lp` was built.
+                let res = self.obj.call_method(py, "lp_state", (&state.obj,), None)
+                    .unwrap();
+                let (rhs, fixed) = res.extract::<(Vec<f64>, Vec<(usize, f64)>)>(py)
+                    .unwrap();
+                return lp.borrow(py).fast_upper_bound(&rhs, &fixed, self.negate);
+            }
+
+            let res = self.obj.call_method(py, "fast_upper_bound", (&state.obj,), None);
+            if let Ok(res) = res {
+                res.extract(py).unwrap()
+            } else {
+                isize::MAX
+            }
+        })
     }
 }
 
-pub struct PyRanking<'a> {
-    gil: Python<'a>,
+pub struct PyRanking {
     obj: PyObject
 }
-unsafe impl Send for PyRanking<'_> {}
-impl <'a> StateRanking for PyRanking<'a> {
-    type State = PyState<'a>;
+impl StateRanking for PyRanking {
+    type State = PyState;
 
     fn compare(&self, a: &Self::State, b: &Self::State) -> std::cmp::Ordering {
-        let res = self.obj.call_method(self.gil, "compare", (&a.obj, &b.obj), None)
-            .unwrap();
-        let res = res.extract::<isize>(self.gil)
-            .unwrap();
-        
-        match res {
-        _ if res == 0 => std::cmp::Ordering::Equal,
-        _ if res <  0 => std::cmp::Ordering::Less,
-        _ =>             std::cmp::Ordering::Greater
-        }
+        Python::with_gil(|py| {
+            let res = self.obj.call_method(py, "compare", (&a.obj, &b.obj), None)
+                .unwrap();
+            let res = res.extract::<isize>(py)
+                .unwrap();
+
+            match res {
+            _ if res == 0 => std::cmp::Ordering::Equal,
+            _ if res <  0 => std::cmp::Ordering::Less,
+            _ =>             std::cmp::Ordering::Greater
+            }
+        })
     }
-}
\ No newline at end of file
+}